@@ -0,0 +1,236 @@
+//! A typed `FromRequest` extractor that performs resource protection during extraction.
+use std::{fmt, marker::PhantomData};
+
+use actix_web::{dev::Payload, web::Data, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use oxide_auth::{
+    endpoint::{Endpoint, WebResponse},
+    primitives::grant::Grant,
+};
+
+use crate::{
+    OAuthRequest, OAuthResource, OAuthResponse, OxideOperation, Resource as ResourceOperation, WebError,
+};
+
+/// A [`Grant`] delivered straight into a handler argument, decoded from the request's bearer
+/// token during extraction.
+pub struct BearerGrant<E> {
+    grant: Grant,
+    _endpoint: PhantomData<fn() -> E>,
+}
+
+impl<E> BearerGrant<E> {
+    /// The decoded grant: subject, client id, scope and expiry of the presented token.
+    pub fn grant(&self) -> &Grant {
+        &self.grant
+    }
+
+    /// Consume the extractor, returning the decoded grant.
+    pub fn into_grant(self) -> Grant {
+        self.grant
+    }
+
+    /// The scope the presented token was issued, for handlers that need finer-grained checks
+    /// than the extractor's required scope.
+    pub fn scope(&self) -> &oxide_auth::endpoint::Scope {
+        &self.grant.scope
+    }
+}
+
+/// Required scope for a [`BearerGrant<E>`] extraction, configured as app `Data`.
+///
+/// Mandatory: register it alongside `Data<E>` with `App::data(RequiredScope(scope))`, so that it
+/// is stored as `Data<RequiredScope>`, which is what extraction looks up. There is a single
+/// `Data<RequiredScope>` slot, not one per `Endpoint` type: every `BearerGrant<E>` reached through
+/// the same app data checks the same scope. A missing or unparsable `RequiredScope` fails
+/// extraction with [`ExtractionError::NotConfigured`] rather than defaulting to "no scope
+/// required".
+pub struct RequiredScope(pub String);
+
+/// Extraction failure for [`BearerGrant`].
+#[derive(Debug)]
+pub enum ExtractionError {
+    /// `Data<E>` or `Data<RequiredScope>` was not registered, or the registered scope does not
+    /// parse. This is a server misconfiguration -- the route isn't wired up -- not something a caller's token
+    /// could ever fix, so it must not be reported the same way as a missing/invalid token.
+    NotConfigured,
+
+    /// The bearer token was missing, invalid, or lacked the required scope, or the request could
+    /// not be checked at all. Carries the challenge response (status, `WWW-Authenticate`, body)
+    /// the resource flow built when it denied the request; when the `Authorization` header is
+    /// malformed or the flow itself fails, it carries a bare `401` with `WWW-Authenticate: Bearer`.
+    Unauthorized(OAuthResponse),
+}
+
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtractionError::NotConfigured => {
+                f.write_str("BearerGrant<E> extractor is missing its Data<E>/Data<RequiredScope>")
+            }
+            ExtractionError::Unauthorized(_) => {
+                f.write_str("request was not authorized for the required scope")
+            }
+        }
+    }
+}
+
+impl ResponseError for ExtractionError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ExtractionError::NotConfigured => HttpResponse::InternalServerError().finish(),
+            ExtractionError::Unauthorized(response) => response.clone().build(None),
+        }
+    }
+}
+
+impl<E> FromRequest for BearerGrant<E>
+where
+    E: Endpoint<OAuthRequest> + Clone + 'static,
+    WebError: From<E::Error>,
+{
+    type Error = ExtractionError;
+    type Future = Result<Self, Self::Error>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        fn bearer_challenge() -> ExtractionError {
+            let mut response = OAuthResponse::ok();
+            let _ = response.unauthorized("Bearer");
+            ExtractionError::Unauthorized(response)
+        }
+
+        let endpoint = req
+            .app_data::<Data<E>>()
+            .map(|data| data.get_ref().clone())
+            .ok_or(ExtractionError::NotConfigured)?;
+        let required = req
+            .app_data::<Data<RequiredScope>>()
+            .map(|scope| scope.0.clone())
+            .ok_or(ExtractionError::NotConfigured)?;
+        let scope = required.parse().map_err(|_| ExtractionError::NotConfigured)?;
+
+        let resource = OAuthResource::new(req).map_err(|_| bearer_challenge())?;
+        match ResourceOperation(resource.into_request(), vec![scope])
+            .run(endpoint)
+            .map_err(|_| bearer_challenge())?
+        {
+            Ok(grant) => Ok(BearerGrant {
+                grant,
+                _endpoint: PhantomData,
+            }),
+            Err(denied) => Err(ExtractionError::Unauthorized(denied)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use oxide_auth::{
+        endpoint::{OwnerSolicitor, Scopes, Template},
+        primitives::{
+            authorizer::Authorizer,
+            issuer::{IssuedToken, Issuer, RefreshedToken},
+            registrar::Registrar,
+        },
+    };
+
+    /// Recognizes no token at all, so every request looks like an anonymous caller -- enough to
+    /// exercise the "missing/invalid bearer token" rejection path without a real token store.
+    #[derive(Clone, Default)]
+    struct NoTokenIssuer;
+
+    impl Issuer for NoTokenIssuer {
+        fn issue(&mut self, _grant: Grant) -> Result<IssuedToken, ()> {
+            Err(())
+        }
+
+        fn refresh(&mut self, _token: &str, _grant: Grant) -> Result<RefreshedToken, ()> {
+            Err(())
+        }
+
+        fn recover_token(&mut self, _token: &str) -> Result<Option<Grant>, ()> {
+            Ok(None)
+        }
+
+        fn recover_refresh(&mut self, _token: &str) -> Result<Option<Grant>, ()> {
+            Ok(None)
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct StubEndpoint {
+        issuer: NoTokenIssuer,
+    }
+
+    impl Endpoint<OAuthRequest> for StubEndpoint {
+        type Error = WebError;
+
+        fn registrar(&self) -> Option<&dyn Registrar> {
+            None
+        }
+
+        fn authorizer_mut(&mut self) -> Option<&mut dyn Authorizer> {
+            None
+        }
+
+        fn issuer_mut(&mut self) -> Option<&mut dyn Issuer> {
+            Some(&mut self.issuer)
+        }
+
+        fn owner_solicitor(&mut self) -> Option<&mut dyn OwnerSolicitor<OAuthRequest>> {
+            None
+        }
+
+        fn scopes(&mut self) -> Option<&mut dyn Scopes<OAuthRequest>> {
+            None
+        }
+
+        fn response(
+            &mut self, _request: &mut OAuthRequest, _kind: Template,
+        ) -> Result<OAuthResponse, Self::Error> {
+            Ok(OAuthResponse::ok())
+        }
+
+        fn error(&mut self, err: oxide_auth::endpoint::OAuthError) -> Self::Error {
+            WebError::Endpoint(err)
+        }
+
+        fn web_error(&mut self, err: WebError) -> Self::Error {
+            err
+        }
+    }
+
+    #[test]
+    fn missing_endpoint_and_scope_data_is_a_server_misconfiguration() {
+        let req = TestRequest::default().to_http_request();
+        let mut payload = Payload::None;
+
+        let err = BearerGrant::<StubEndpoint>::from_request(&req, &mut payload).unwrap_err();
+        assert!(matches!(err, ExtractionError::NotConfigured));
+    }
+
+    #[test]
+    fn missing_required_scope_is_a_server_misconfiguration_not_a_bearer_challenge() {
+        let req = TestRequest::default()
+            .data(StubEndpoint::default())
+            .to_http_request();
+        let mut payload = Payload::None;
+
+        let err = BearerGrant::<StubEndpoint>::from_request(&req, &mut payload).unwrap_err();
+        assert!(matches!(err, ExtractionError::NotConfigured));
+    }
+
+    #[test]
+    fn absent_bearer_token_is_unauthorized_once_fully_configured() {
+        let req = TestRequest::default()
+            .data(StubEndpoint::default())
+            .data(RequiredScope("read".into()))
+            .to_http_request();
+        let mut payload = Payload::None;
+
+        let err = BearerGrant::<StubEndpoint>::from_request(&req, &mut payload).unwrap_err();
+        assert!(matches!(err, ExtractionError::Unauthorized(_)));
+    }
+}
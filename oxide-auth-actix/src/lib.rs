@@ -2,6 +2,9 @@
 //!
 //! Use the provided methods to use code grant methods in an asynchronous fashion, or use an
 //! `AsActor<_>` to create an actor implementing endpoint functionality via messages.
+//!
+//! Responses built with [`OAuthResponse::compressed`] are gzip/deflate/br-encoded on demand,
+//! using `flate2` and `brotli`.
 #![warn(missing_docs)]
 
 use actix::{MailboxError, Message};
@@ -10,8 +13,8 @@ use actix_web::{
     error::BlockingError,
     http::{
         header::{
-            HeaderMap, InvalidHeaderValue, InvalidHeaderValueBytes, AUTHORIZATION, CONTENT_TYPE,
-            LOCATION, WWW_AUTHENTICATE,
+            HeaderMap, InvalidHeaderValue, InvalidHeaderValueBytes, ACCEPT_ENCODING, AUTHORIZATION,
+            CONTENT_ENCODING, CONTENT_TYPE, LOCATION, VARY, WWW_AUTHENTICATE,
         },
         HttpTryFrom, StatusCode,
     },
@@ -30,14 +33,24 @@ use oxide_auth::{
 use std::{error, fmt};
 use url::Url;
 
+mod extractor;
+mod middleware;
 mod operations;
+mod solicitor;
 
-pub use operations::{Authorize, Refresh, Resource, Token};
+pub use extractor::{BearerGrant, ExtractionError, RequiredScope};
+pub use middleware::{RequireScope, RequireScopeMiddleware};
+pub use operations::{Authorize, OxideOperationAsync, Refresh, Resource, Token};
+pub use solicitor::AsyncOwnerSolicitor;
 
 /// Describes an operation that can be performed in the presence of an `Endpoint`
 ///
 /// This trait can be implemented by any type, but is very useful in Actor scenarios, where an
 /// Actor can provide an endpoint to an operation sent as a message.
+///
+/// For endpoints backed by the async primitives in `oxide-auth-async`, see the mirrored
+/// [`operations::OxideOperationAsync`] trait, which drives the same flows with `.await` instead
+/// of blocking the caller.
 pub trait OxideOperation: Sized + 'static {
     /// The success-type produced by an OxideOperation
     type Item: 'static;
@@ -85,6 +98,7 @@ pub struct OAuthResponse {
     status: StatusCode,
     headers: HeaderMap,
     body: Option<String>,
+    compress: bool,
 }
 
 #[derive(Debug)]
@@ -206,9 +220,17 @@ impl OAuthResponse {
             status: StatusCode::OK,
             headers: HeaderMap::new(),
             body: None,
+            compress: false,
         }
     }
 
+    /// Opt this response into negotiated compression (gzip/deflate/br) of its body, based on the
+    /// originating request's `Accept-Encoding`.
+    pub fn compressed(mut self, enabled: bool) -> Self {
+        self.compress = enabled;
+        self
+    }
+
     /// Set the `ContentType` header on a response
     pub fn content_type(mut self, content_type: &str) -> Result<Self, WebError> {
         self.headers
@@ -324,24 +346,176 @@ impl FromRequest for OAuthResource {
     }
 }
 
-impl Responder for OAuthResponse {
-    type Error = WebError;
-    type Future = Result<HttpResponse, Self::Error>;
+/// Bodies smaller than this aren't worth the CPU cost of compressing; mirrors the default
+/// threshold actix-web's own `Compress` middleware uses.
+const COMPRESSION_THRESHOLD: usize = 860;
+
+#[derive(Clone, Copy)]
+enum ContentCoding {
+    Brotli,
+    Gzip,
+    Deflate,
+}
 
-    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+impl ContentCoding {
+    fn as_str(self) -> &'static str {
+        match self {
+            ContentCoding::Brotli => "br",
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+        }
+    }
+
+    /// Pick the coding to compress with, preferring whichever of br/gzip/deflate the request's
+    /// `Accept-Encoding` allows, in that order. A coding listed with `q=0` (RFC 7231 §5.3.4) is
+    /// explicitly refused and is never chosen.
+    fn negotiate(req: &HttpRequest) -> Option<Self> {
+        let header = req.headers().get(ACCEPT_ENCODING)?.to_str().ok()?;
+        let offered: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|part| {
+                let mut segments = part.split(';');
+                let token = segments.next()?.trim();
+                if token.is_empty() {
+                    return None;
+                }
+                let q = segments
+                    .find_map(|param| param.trim().strip_prefix("q=")?.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((token, q))
+            })
+            .collect();
+
+        [ContentCoding::Brotli, ContentCoding::Gzip, ContentCoding::Deflate]
+            .iter()
+            .copied()
+            .find(|coding| {
+                offered
+                    .iter()
+                    .any(|(token, q)| token.eq_ignore_ascii_case(coding.as_str()) && *q > 0.0)
+            })
+    }
+
+    fn compress(self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        use std::io::Write;
+
+        match self {
+            ContentCoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            ContentCoding::Deflate => {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            ContentCoding::Brotli => {
+                let mut output = Vec::new();
+                brotli::CompressorWriter::new(&mut output, 4096, 5, 22).write_all(body)?;
+                Ok(output)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod content_coding_tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn request_with_accept_encoding(value: &str) -> HttpRequest {
+        TestRequest::with_header(ACCEPT_ENCODING, value).to_http_request()
+    }
+
+    #[test]
+    fn no_header_means_no_negotiated_coding() {
+        let req = TestRequest::default().to_http_request();
+        assert!(ContentCoding::negotiate(&req).is_none());
+    }
+
+    #[test]
+    fn prefers_brotli_over_gzip_and_deflate() {
+        let req = request_with_accept_encoding("gzip, deflate, br");
+        assert_eq!(ContentCoding::negotiate(&req).map(ContentCoding::as_str), Some("br"));
+    }
+
+    #[test]
+    fn falls_back_to_an_offered_coding() {
+        let req = request_with_accept_encoding("gzip");
+        assert_eq!(ContentCoding::negotiate(&req).map(ContentCoding::as_str), Some("gzip"));
+    }
+
+    #[test]
+    fn q_zero_refuses_a_coding() {
+        let req = request_with_accept_encoding("br;q=0, gzip;q=0, deflate");
+        assert_eq!(ContentCoding::negotiate(&req).map(ContentCoding::as_str), Some("deflate"));
+    }
+
+    #[test]
+    fn q_zero_on_every_coding_negotiates_nothing() {
+        let req = request_with_accept_encoding("br;q=0, gzip;q=0, deflate;q=0");
+        assert!(ContentCoding::negotiate(&req).is_none());
+    }
+
+    #[test]
+    fn gzip_round_trips() {
+        let compressed = ContentCoding::Gzip.compress(b"hello world").unwrap();
+        let mut decoder = flate2::read::GzDecoder::new(&compressed[..]);
+        let mut out = String::new();
+        std::io::Read::read_to_string(&mut decoder, &mut out).unwrap();
+        assert_eq!(out, "hello world");
+    }
+}
+
+impl OAuthResponse {
+    /// Build the `HttpResponse` this response describes.
+    ///
+    /// `req` is used only to negotiate compression when [`OAuthResponse::compressed`] opted in;
+    /// pass `None` from call sites with no originating request to hand (compression is then
+    /// skipped).
+    pub(crate) fn build(self, req: Option<&HttpRequest>) -> HttpResponse {
         let mut builder = HttpResponseBuilder::new(self.status);
         for (k, v) in self.headers.into_iter() {
             builder.header(k, v.to_owned());
         }
 
-        if let Some(body) = self.body {
-            Ok(builder.body(body))
+        let coding = if self.compress {
+            // The body varies by `Accept-Encoding` whenever compression was even considered, not
+            // only when it was actually applied, so a shared cache never serves a negotiated body
+            // under a plain URL key.
+            builder.header(VARY, ACCEPT_ENCODING.as_str());
+            req.and_then(ContentCoding::negotiate)
         } else {
-            Ok(builder.finish())
+            None
+        };
+
+        match (self.body, coding) {
+            (Some(body), Some(coding)) if body.len() >= COMPRESSION_THRESHOLD => {
+                match coding.compress(body.as_bytes()) {
+                    Ok(compressed) => {
+                        builder.header(CONTENT_ENCODING, coding.as_str());
+                        builder.body(compressed)
+                    }
+                    Err(_) => builder.body(body),
+                }
+            }
+            (Some(body), _) => builder.body(body),
+            (None, _) => builder.finish(),
         }
     }
 }
 
+impl Responder for OAuthResponse {
+    type Error = WebError;
+    type Future = Result<HttpResponse, Self::Error>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        Ok(self.build(Some(req)))
+    }
+}
+
 impl From<OAuthResource> for OAuthRequest {
     fn from(o: OAuthResource) -> Self {
         o.into_request()
@@ -354,6 +528,7 @@ impl Default for OAuthResponse {
             status: StatusCode::OK,
             headers: HeaderMap::new(),
             body: None,
+            compress: false,
         }
     }
 }
@@ -455,6 +630,128 @@ impl error::Error for WebError {
     }
 }
 
+impl WebError {
+    /// The HTTP status this failure should be reported with.
+    ///
+    /// Client mistakes (a malformed form, a missing query, a bad `Authorization` header) are
+    /// `400`s, not `500`s; a full mailbox or a canceled operation means the server is
+    /// overloaded, not broken, hence `503`; only header construction failures and
+    /// `OAuthError::PrimitiveError` -- a bug in a primitive, not a client mistake -- stay `500`.
+    fn status_code(&self) -> StatusCode {
+        match self {
+            WebError::Form
+            | WebError::Query
+            | WebError::Body
+            | WebError::Encoding
+            | WebError::Authorization => StatusCode::BAD_REQUEST,
+            WebError::Mailbox | WebError::Canceled => StatusCode::SERVICE_UNAVAILABLE,
+            WebError::Header(_) | WebError::HeaderBytes(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            WebError::Endpoint(OAuthError::DenySilently) => StatusCode::BAD_REQUEST,
+            WebError::Endpoint(OAuthError::PrimitiveError) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The RFC 6749 `error` code for this failure, or `None` when it's an internal/transport
+    /// failure that doesn't warrant an OAuth-protocol error body (header construction, a full
+    /// actor mailbox, a canceled operation, a primitive bug).
+    ///
+    /// The flows themselves already render the finer-grained codes (`invalid_client`,
+    /// `invalid_grant`, `unauthorized_client`, `unsupported_grant_type`, `invalid_scope`) into a
+    /// conformant `OAuthResponse` body before a `WebError` is ever produced; the client errors that
+    /// reach here are requests the frontend couldn't even hand to the flow, or ones the flow
+    /// refused silently (`OAuthError::DenySilently`) without rendering a body, so
+    /// `invalid_request` is the code for all of them.
+    fn oauth_error_code(&self) -> Option<&'static str> {
+        match self {
+            WebError::Form
+            | WebError::Query
+            | WebError::Body
+            | WebError::Encoding
+            | WebError::Authorization
+            | WebError::Endpoint(OAuthError::DenySilently) => Some("invalid_request"),
+            _ => None,
+        }
+    }
+}
+
 impl ResponseError for WebError {
-    // Default to 500 for now
+    fn error_response(&self) -> HttpResponse {
+        let mut builder = HttpResponseBuilder::new(self.status_code());
+
+        match self.oauth_error_code() {
+            Some(error) => builder
+                .header(CONTENT_TYPE, "application/json")
+                .body(format!(
+                    r#"{{"error":"{}","error_description":"{}"}}"#,
+                    error,
+                    json_escape(&self.to_string())
+                )),
+            None => builder.finish(),
+        }
+    }
+}
+
+/// Minimal JSON string escaping, good enough for the `Display` text of a `WebError` without
+/// pulling in a JSON library for one field.
+fn json_escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for ch in input.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod web_error_tests {
+    use super::*;
+
+    #[test]
+    fn client_mistakes_are_bad_request() {
+        for err in &[
+            WebError::Form,
+            WebError::Query,
+            WebError::Body,
+            WebError::Encoding,
+            WebError::Authorization,
+        ] {
+            assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+            assert_eq!(err.oauth_error_code(), Some("invalid_request"));
+        }
+    }
+
+    #[test]
+    fn actor_failures_are_service_unavailable_with_no_oauth_body() {
+        assert_eq!(WebError::Mailbox.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(WebError::Canceled.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(WebError::Mailbox.oauth_error_code(), None);
+        assert_eq!(WebError::Canceled.oauth_error_code(), None);
+    }
+
+    #[test]
+    fn primitive_error_is_internal_server_error() {
+        let err = WebError::Endpoint(OAuthError::PrimitiveError);
+        assert_eq!(err.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.oauth_error_code(), None);
+    }
+
+    #[test]
+    fn deny_silently_is_bad_request_invalid_request() {
+        let err = WebError::Endpoint(OAuthError::DenySilently);
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.oauth_error_code(), Some("invalid_request"));
+    }
+
+    #[test]
+    fn json_escape_handles_quotes_and_control_characters() {
+        assert_eq!(json_escape(r#"say "hi"\n"#), r#"say \"hi\"\\n"#);
+        assert_eq!(json_escape("line\nbreak"), "line\\nbreak");
+    }
 }
@@ -0,0 +1,291 @@
+//! Middleware guarding a scope of routes with bearer-token resource protection.
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use actix_service::{Service, Transform};
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    Error as ActixError, HttpMessage, HttpResponse, ResponseError,
+};
+use futures::{
+    future::{err, ok, FutureResult},
+    Future, Poll,
+};
+use oxide_auth::{endpoint::Endpoint, primitives::grant::Grant};
+
+use crate::{OAuthRequest, OAuthResource, OAuthResponse, OxideOperation, Resource as ResourceOperation, WebError};
+
+/// Guards a scope of routes with oxide-auth's resource flow, requiring a bearer token carrying
+/// `scope` before the wrapped service runs.
+///
+/// On success the validated [`Grant`] is stashed in the request extensions, so downstream
+/// handlers can pull it back out with `req.extensions().get::<Grant>()`. Register with
+/// `.wrap(RequireScope::new(scope, make_endpoint))` on an `App`/`Scope`. Call
+/// [`RequireScope::optional`] to let anonymous requests through while still populating the grant
+/// whenever a valid token is present.
+pub struct RequireScope<F> {
+    make_endpoint: Rc<F>,
+    scope: String,
+    optional: bool,
+}
+
+impl<F> RequireScope<F> {
+    /// Require a bearer token carrying `scope`, building a fresh `Endpoint` per request with
+    /// `make_endpoint`.
+    pub fn new(scope: impl Into<String>, make_endpoint: F) -> Self {
+        RequireScope {
+            make_endpoint: Rc::new(make_endpoint),
+            scope: scope.into(),
+            optional: false,
+        }
+    }
+
+    /// Let requests without a valid bearer token through to the wrapped service instead of
+    /// rejecting them with `401`/`403`.
+    ///
+    /// A [`Grant`] is still stashed in the request extensions whenever a valid, sufficiently
+    /// scoped token is present, so handlers can branch on `Option<Grant>` rather than requiring
+    /// one unconditionally.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+impl<S, B, F, E> Transform<S> for RequireScope<F>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = ActixError> + 'static,
+    S::Future: 'static,
+    F: Fn() -> E + 'static,
+    E: Endpoint<OAuthRequest> + 'static,
+    WebError: From<E::Error>,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = ActixError;
+    type Transform = RequireScopeMiddleware<S, F>;
+    type InitError = ();
+    type Future = FutureResult<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequireScopeMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            make_endpoint: self.make_endpoint.clone(),
+            scope: self.scope.clone(),
+            optional: self.optional,
+        })
+    }
+}
+
+/// The [`Service`] produced by [`RequireScope`]; see that type for behavior.
+pub struct RequireScopeMiddleware<S, F> {
+    service: Rc<RefCell<S>>,
+    make_endpoint: Rc<F>,
+    scope: String,
+    optional: bool,
+}
+
+impl<S, B, F, E> Service for RequireScopeMiddleware<S, F>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = ActixError> + 'static,
+    S::Future: 'static,
+    F: Fn() -> E + 'static,
+    E: Endpoint<OAuthRequest> + 'static,
+    WebError: From<E::Error>,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = ActixError;
+    type Future = Box<dyn Future<Item = Self::Response, Error = Self::Error>>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.borrow_mut().poll_ready()
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let resource = match OAuthResource::new(req.request()) {
+            Ok(resource) => resource,
+            Err(_) if self.optional => return Box::new(self.service.borrow_mut().call(req)),
+            Err(web_err) => return Box::new(err(ActixError::from(web_err))),
+        };
+
+        let required = match self.scope.parse() {
+            Ok(required) => required,
+            Err(_) if self.optional => return Box::new(self.service.borrow_mut().call(req)),
+            Err(_) => {
+                return Box::new(err(actix_web::error::ErrorInternalServerError(
+                    "RequireScope is configured with a scope that does not parse",
+                )))
+            }
+        };
+
+        match ResourceOperation(resource.into_request(), vec![required]).run((self.make_endpoint)()) {
+            Ok(Ok(grant)) => {
+                req.extensions_mut().insert::<Grant>(grant);
+                Box::new(self.service.borrow_mut().call(req))
+            }
+            Ok(Err(_challenge)) if self.optional => Box::new(self.service.borrow_mut().call(req)),
+            Ok(Err(challenge)) => Box::new(err(ActixError::from(Challenge(challenge)))),
+            Err(_) if self.optional => Box::new(self.service.borrow_mut().call(req)),
+            Err(web_err) => Box::new(err(ActixError::from(web_err))),
+        }
+    }
+}
+
+/// The response a denied resource flow already built (status, `WWW-Authenticate`, body), wrapped
+/// so it can be propagated through `Service::Error` and rendered by actix-web's error machinery
+/// regardless of the wrapped service's body type.
+struct Challenge(OAuthResponse);
+
+impl fmt::Debug for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Challenge").finish()
+    }
+}
+
+impl fmt::Display for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("request did not carry a sufficiently scoped bearer token")
+    }
+}
+
+impl ResponseError for Challenge {
+    fn error_response(&self) -> HttpResponse {
+        self.0.clone().build(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use futures::Async;
+    use oxide_auth::{
+        endpoint::{OwnerSolicitor, Scopes, Template},
+        primitives::{
+            authorizer::Authorizer,
+            issuer::{IssuedToken, Issuer, RefreshedToken},
+            registrar::Registrar,
+        },
+    };
+
+    /// Recognizes no token at all, so every request looks like an anonymous caller -- enough to
+    /// exercise the rejection path without a real token store.
+    #[derive(Clone, Default)]
+    struct NoTokenIssuer;
+
+    impl Issuer for NoTokenIssuer {
+        fn issue(&mut self, _grant: Grant) -> Result<IssuedToken, ()> {
+            Err(())
+        }
+
+        fn refresh(&mut self, _token: &str, _grant: Grant) -> Result<RefreshedToken, ()> {
+            Err(())
+        }
+
+        fn recover_token(&mut self, _token: &str) -> Result<Option<Grant>, ()> {
+            Ok(None)
+        }
+
+        fn recover_refresh(&mut self, _token: &str) -> Result<Option<Grant>, ()> {
+            Ok(None)
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct StubEndpoint {
+        issuer: NoTokenIssuer,
+    }
+
+    impl Endpoint<OAuthRequest> for StubEndpoint {
+        type Error = WebError;
+
+        fn registrar(&self) -> Option<&dyn Registrar> {
+            None
+        }
+
+        fn authorizer_mut(&mut self) -> Option<&mut dyn Authorizer> {
+            None
+        }
+
+        fn issuer_mut(&mut self) -> Option<&mut dyn Issuer> {
+            Some(&mut self.issuer)
+        }
+
+        fn owner_solicitor(&mut self) -> Option<&mut dyn OwnerSolicitor<OAuthRequest>> {
+            None
+        }
+
+        fn scopes(&mut self) -> Option<&mut dyn Scopes<OAuthRequest>> {
+            None
+        }
+
+        fn response(
+            &mut self, _request: &mut OAuthRequest, _kind: Template,
+        ) -> Result<OAuthResponse, Self::Error> {
+            Ok(OAuthResponse::ok())
+        }
+
+        fn error(&mut self, err: oxide_auth::endpoint::OAuthError) -> Self::Error {
+            WebError::Endpoint(err)
+        }
+
+        fn web_error(&mut self, err: WebError) -> Self::Error {
+            err
+        }
+    }
+
+    struct PassThrough;
+
+    impl Service for PassThrough {
+        type Request = ServiceRequest;
+        type Response = ServiceResponse;
+        type Error = ActixError;
+        type Future = FutureResult<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: ServiceRequest) -> Self::Future {
+            ok(req.into_response(HttpResponse::Ok().finish().into_body()))
+        }
+    }
+
+    fn middleware(
+        scope: &str, optional: bool,
+    ) -> RequireScopeMiddleware<PassThrough, fn() -> StubEndpoint> {
+        let make_endpoint = StubEndpoint::default as fn() -> StubEndpoint;
+        let transform = if optional {
+            RequireScope::new(scope, make_endpoint).optional()
+        } else {
+            RequireScope::new(scope, make_endpoint)
+        };
+        transform.new_transform(PassThrough).wait().unwrap()
+    }
+
+    #[test]
+    fn rejects_a_request_with_no_bearer_token() {
+        let mut mw = middleware("read", false);
+        let req = TestRequest::default().to_srv_request();
+        assert!(mw.call(req).wait().is_err());
+    }
+
+    #[test]
+    fn optional_lets_an_unauthenticated_request_through() {
+        let mut mw = middleware("read", true);
+        let req = TestRequest::default().to_srv_request();
+        assert!(mw.call(req).wait().is_ok());
+    }
+
+    #[test]
+    fn two_instances_keep_independent_scope_configuration() {
+        let make_endpoint = StubEndpoint::default as fn() -> StubEndpoint;
+        let admin = RequireScope::new("admin", make_endpoint);
+        let read = RequireScope::new("read", make_endpoint);
+        assert_ne!(admin.scope, read.scope);
+    }
+}
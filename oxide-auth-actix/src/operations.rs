@@ -0,0 +1,389 @@
+//! `OxideOperation` implementations for the four standard OAuth flows.
+//!
+//! Each operation bundles the request (and any extra data the flow needs) so that it can be sent
+//! to an `AsActor<_>` as an `OxideMessage`, run synchronously via `OxideOperation::run`, or driven
+//! to completion with `.await` via `OxideOperationAsync::run_async` against an async endpoint.
+use std::future::Future;
+use std::pin::Pin;
+
+use oxide_auth::{
+    endpoint::{Endpoint, OwnerSolicitor, Scope, Scopes, Template},
+    frontends::simple::endpoint::{AccessTokenFlow, AuthorizationFlow, RefreshFlow, ResourceFlow},
+    primitives::{
+        authorizer::Authorizer, grant::Grant, issuer::Issuer, registrar::Registrar,
+    },
+};
+use oxide_auth_async::endpoint::{
+    access_token::AccessTokenFlow as AsyncAccessTokenFlow,
+    authorization::AuthorizationFlow as AsyncAuthorizationFlow, refresh::RefreshFlow as AsyncRefreshFlow,
+    resource::ResourceFlow as AsyncResourceFlow, Endpoint as AsyncEndpoint,
+};
+use oxide_auth_async::primitives::{
+    Authorizer as AsyncAuthorizer, Issuer as AsyncIssuer, OwnerSolicitor as AsyncOwnerSolicitor,
+    Registrar as AsyncRegistrar,
+};
+
+use crate::{OAuthRequest, OAuthResponse, OxideOperation, WebError};
+
+/// Run the authorization code flow for the contained request.
+///
+/// When run via [`OxideOperationAsync::run_async`], consent is decided by whatever
+/// `oxide_auth_async`-compatible solicitor the endpoint returns from `owner_solicitor()`; see
+/// [`crate::AsyncOwnerSolicitor`] for a solicitor that can await external state (sessions,
+/// cookies, a database) before answering.
+pub struct Authorize(pub OAuthRequest);
+
+/// Run the access token flow for the contained request.
+pub struct Token(pub OAuthRequest);
+
+/// Run the refresh token flow for the contained request.
+pub struct Refresh(pub OAuthRequest);
+
+/// Run the resource flow for the contained request, checking it against `scopes`.
+pub struct Resource(pub OAuthRequest, pub Vec<Scope>);
+
+/// A fixed list of acceptable scopes, any one of which satisfies the resource flow's check.
+struct ExactScopes(Vec<Scope>);
+
+impl Scopes<OAuthRequest> for ExactScopes {
+    fn scopes(&mut self, _request: &mut OAuthRequest) -> &[Scope] {
+        &self.0
+    }
+}
+
+/// Wraps an `Endpoint`, overriding `scopes()` so a particular [`Resource`] operation's own scope
+/// list -- not whatever the wrapped endpoint configures globally -- is what the flow actually
+/// checks the request against.
+struct WithScopes<'e, E> {
+    inner: &'e mut E,
+    scopes: ExactScopes,
+}
+
+impl<'e, E> WithScopes<'e, E> {
+    fn new(inner: &'e mut E, scopes: Vec<Scope>) -> Self {
+        WithScopes { inner, scopes: ExactScopes(scopes) }
+    }
+}
+
+impl<'e, E> Endpoint<OAuthRequest> for WithScopes<'e, E>
+where
+    E: Endpoint<OAuthRequest>,
+{
+    type Error = E::Error;
+
+    fn registrar(&self) -> Option<&dyn Registrar> {
+        self.inner.registrar()
+    }
+
+    fn authorizer_mut(&mut self) -> Option<&mut dyn Authorizer> {
+        self.inner.authorizer_mut()
+    }
+
+    fn issuer_mut(&mut self) -> Option<&mut dyn Issuer> {
+        self.inner.issuer_mut()
+    }
+
+    fn owner_solicitor(&mut self) -> Option<&mut dyn OwnerSolicitor<OAuthRequest>> {
+        self.inner.owner_solicitor()
+    }
+
+    fn scopes(&mut self) -> Option<&mut dyn Scopes<OAuthRequest>> {
+        Some(&mut self.scopes)
+    }
+
+    fn response(
+        &mut self, request: &mut OAuthRequest, kind: Template,
+    ) -> Result<OAuthResponse, Self::Error> {
+        self.inner.response(request, kind)
+    }
+
+    fn error(&mut self, err: oxide_auth::endpoint::OAuthError) -> Self::Error {
+        self.inner.error(err)
+    }
+
+    fn web_error(&mut self, err: WebError) -> Self::Error {
+        self.inner.web_error(err)
+    }
+}
+
+impl<'e, E> AsyncEndpoint<OAuthRequest> for WithScopes<'e, E>
+where
+    E: AsyncEndpoint<OAuthRequest>,
+{
+    type Error = E::Error;
+
+    fn registrar(&self) -> Option<&dyn AsyncRegistrar> {
+        self.inner.registrar()
+    }
+
+    fn authorizer_mut(&mut self) -> Option<&mut dyn AsyncAuthorizer> {
+        self.inner.authorizer_mut()
+    }
+
+    fn issuer_mut(&mut self) -> Option<&mut dyn AsyncIssuer> {
+        self.inner.issuer_mut()
+    }
+
+    fn owner_solicitor(&mut self) -> Option<&mut dyn AsyncOwnerSolicitor<OAuthRequest>> {
+        self.inner.owner_solicitor()
+    }
+
+    fn scopes(&mut self) -> Option<&mut dyn Scopes<OAuthRequest>> {
+        Some(&mut self.scopes)
+    }
+
+    fn response(
+        &mut self, request: &mut OAuthRequest, kind: Template,
+    ) -> Result<OAuthResponse, Self::Error> {
+        self.inner.response(request, kind)
+    }
+
+    fn error(&mut self, err: oxide_auth::endpoint::OAuthError) -> Self::Error {
+        self.inner.error(err)
+    }
+
+    fn web_error(&mut self, err: WebError) -> Self::Error {
+        self.inner.web_error(err)
+    }
+}
+
+/// Mirrors `OxideOperation`, but drives the flow against an async endpoint and returns a boxed
+/// future instead of blocking the caller.
+pub trait OxideOperationAsync: Sized + 'static {
+    /// The success-type produced by an OxideOperationAsync
+    type Item: 'static;
+
+    /// The error type produced by an OxideOperationAsync
+    type Error: 'static;
+
+    /// Performs the oxide operation with the provided async endpoint
+    fn run_async<'e, E>(
+        self, endpoint: &'e mut E,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Item, Self::Error>> + 'e>>
+    where
+        E: AsyncEndpoint<OAuthRequest> + 'e,
+        WebError: From<E::Error>;
+}
+
+impl OxideOperation for Authorize {
+    type Item = OAuthResponse;
+    type Error = WebError;
+
+    fn run<E>(self, mut endpoint: E) -> Result<Self::Item, Self::Error>
+    where
+        E: Endpoint<OAuthRequest>,
+        WebError: From<E::Error>,
+    {
+        AuthorizationFlow::prepare(&mut endpoint)?
+            .execute(self.0)
+            .map_err(WebError::from)
+    }
+}
+
+impl OxideOperationAsync for Authorize {
+    type Item = OAuthResponse;
+    type Error = WebError;
+
+    fn run_async<'e, E>(
+        self, endpoint: &'e mut E,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Item, Self::Error>> + 'e>>
+    where
+        E: AsyncEndpoint<OAuthRequest> + 'e,
+        WebError: From<E::Error>,
+    {
+        Box::pin(async move {
+            let mut flow = AsyncAuthorizationFlow::prepare(endpoint)?;
+            flow.execute(self.0).await.map_err(WebError::from)
+        })
+    }
+}
+
+impl OxideOperation for Token {
+    type Item = OAuthResponse;
+    type Error = WebError;
+
+    fn run<E>(self, mut endpoint: E) -> Result<Self::Item, Self::Error>
+    where
+        E: Endpoint<OAuthRequest>,
+        WebError: From<E::Error>,
+    {
+        AccessTokenFlow::prepare(&mut endpoint)?
+            .execute(self.0)
+            .map_err(WebError::from)
+    }
+}
+
+impl OxideOperationAsync for Token {
+    type Item = OAuthResponse;
+    type Error = WebError;
+
+    fn run_async<'e, E>(
+        self, endpoint: &'e mut E,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Item, Self::Error>> + 'e>>
+    where
+        E: AsyncEndpoint<OAuthRequest> + 'e,
+        WebError: From<E::Error>,
+    {
+        Box::pin(async move {
+            let mut flow = AsyncAccessTokenFlow::prepare(endpoint)?;
+            flow.execute(self.0).await.map_err(WebError::from)
+        })
+    }
+}
+
+impl OxideOperation for Refresh {
+    type Item = OAuthResponse;
+    type Error = WebError;
+
+    fn run<E>(self, mut endpoint: E) -> Result<Self::Item, Self::Error>
+    where
+        E: Endpoint<OAuthRequest>,
+        WebError: From<E::Error>,
+    {
+        RefreshFlow::prepare(&mut endpoint)?
+            .execute(self.0)
+            .map_err(WebError::from)
+    }
+}
+
+impl OxideOperationAsync for Refresh {
+    type Item = OAuthResponse;
+    type Error = WebError;
+
+    fn run_async<'e, E>(
+        self, endpoint: &'e mut E,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Item, Self::Error>> + 'e>>
+    where
+        E: AsyncEndpoint<OAuthRequest> + 'e,
+        WebError: From<E::Error>,
+    {
+        Box::pin(async move {
+            let mut flow = AsyncRefreshFlow::prepare(endpoint)?;
+            flow.execute(self.0).await.map_err(WebError::from)
+        })
+    }
+}
+
+impl OxideOperation for Resource {
+    type Item = Result<Grant, OAuthResponse>;
+    type Error = WebError;
+
+    fn run<E>(self, mut endpoint: E) -> Result<Self::Item, Self::Error>
+    where
+        E: Endpoint<OAuthRequest>,
+        WebError: From<E::Error>,
+    {
+        let mut endpoint = WithScopes::new(&mut endpoint, self.1);
+        match ResourceFlow::prepare(&mut endpoint)?.execute(self.0) {
+            Ok(grant) => Ok(Ok(grant)),
+            Err(Ok(denied)) => Ok(Err(denied)),
+            Err(Err(err)) => Err(WebError::from(err)),
+        }
+    }
+}
+
+impl OxideOperationAsync for Resource {
+    type Item = Result<Grant, OAuthResponse>;
+    type Error = WebError;
+
+    fn run_async<'e, E>(
+        self, endpoint: &'e mut E,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Item, Self::Error>> + 'e>>
+    where
+        E: AsyncEndpoint<OAuthRequest> + 'e,
+        WebError: From<E::Error>,
+    {
+        Box::pin(async move {
+            let mut endpoint = WithScopes::new(endpoint, self.1);
+            let mut flow = AsyncResourceFlow::prepare(&mut endpoint)?;
+            match flow.execute(self.0).await {
+                Ok(grant) => Ok(Ok(grant)),
+                Err(Ok(denied)) => Ok(Err(denied)),
+                Err(Err(err)) => Err(WebError::from(err)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod resource_scope_tests {
+    use super::*;
+
+    struct NoopEndpoint;
+
+    impl Endpoint<OAuthRequest> for NoopEndpoint {
+        type Error = WebError;
+
+        fn registrar(&self) -> Option<&dyn Registrar> {
+            None
+        }
+
+        fn authorizer_mut(&mut self) -> Option<&mut dyn Authorizer> {
+            None
+        }
+
+        fn issuer_mut(&mut self) -> Option<&mut dyn Issuer> {
+            None
+        }
+
+        fn owner_solicitor(&mut self) -> Option<&mut dyn OwnerSolicitor<OAuthRequest>> {
+            None
+        }
+
+        fn scopes(&mut self) -> Option<&mut dyn Scopes<OAuthRequest>> {
+            // Intentionally `None`: these tests exist to prove a `Resource` operation's own
+            // scopes reach the flow through `WithScopes`, not through whatever the wrapped
+            // endpoint configures globally.
+            None
+        }
+
+        fn response(
+            &mut self, _request: &mut OAuthRequest, _kind: Template,
+        ) -> Result<OAuthResponse, Self::Error> {
+            Ok(OAuthResponse::ok())
+        }
+
+        fn error(&mut self, err: oxide_auth::endpoint::OAuthError) -> Self::Error {
+            WebError::Endpoint(err)
+        }
+
+        fn web_error(&mut self, err: WebError) -> Self::Error {
+            err
+        }
+    }
+
+    fn dummy_request() -> OAuthRequest {
+        OAuthRequest {
+            auth: None,
+            query: None,
+            body: None,
+        }
+    }
+
+    #[test]
+    fn with_scopes_overrides_the_wrapped_endpoints_scopes() {
+        let read: Scope = "read".parse().unwrap();
+        let mut inner = NoopEndpoint;
+        let mut wrapped = WithScopes::new(&mut inner, vec![read.clone()]);
+
+        let configured = Endpoint::scopes(&mut wrapped).unwrap().scopes(&mut dummy_request());
+        assert_eq!(configured, &[read]);
+    }
+
+    #[test]
+    fn distinct_resource_operations_carry_distinct_scopes_through_the_same_endpoint() {
+        let admin: Scope = "admin".parse().unwrap();
+        let read: Scope = "read".parse().unwrap();
+
+        let mut admin_inner = NoopEndpoint;
+        let mut admin_endpoint = WithScopes::new(&mut admin_inner, vec![admin.clone()]);
+        let admin_checked = Endpoint::scopes(&mut admin_endpoint).unwrap().scopes(&mut dummy_request());
+        assert_eq!(admin_checked, &[admin]);
+        assert_ne!(admin_checked, &[read.clone()][..]);
+
+        let mut read_inner = NoopEndpoint;
+        let mut read_endpoint = WithScopes::new(&mut read_inner, vec![read.clone()]);
+        let read_checked = Endpoint::scopes(&mut read_endpoint).unwrap().scopes(&mut dummy_request());
+        assert_eq!(read_checked, &[read]);
+    }
+}
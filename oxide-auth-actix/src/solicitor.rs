@@ -0,0 +1,136 @@
+//! An async counterpart to `oxide_auth::endpoint::OwnerSolicitor`.
+use std::future::Future;
+use std::pin::Pin;
+
+use oxide_auth::endpoint::{OwnerConsent, Solicitation};
+use oxide_auth_async::primitives::OwnerSolicitor as AsyncOwnerSolicitorPrimitive;
+
+use crate::{OAuthRequest, OAuthResponse};
+
+/// Determines the owner's consent to client access, potentially awaiting external state before
+/// answering.
+///
+/// Authorization consent can depend on more than what's present in the request itself -- a
+/// signed cookie, a session lookup in a store, a database round-trip -- and those checks are
+/// naturally asynchronous. Implement this trait to consult that state before deciding
+/// `Authorized`/`Denied`/`InProgress`.
+///
+/// The `InProgress` variant carries a rendered [`OAuthResponse`] (for example an askama consent
+/// page), so a solicitor can send the user through a consent round-trip without losing the
+/// original authorization request; the CSRF token and redirect state live in that response and
+/// the session store, and are picked back up the next time `check_consent` runs for the
+/// continuation request.
+pub trait AsyncOwnerSolicitor {
+    /// Determine the owner's consent, awaiting any external state the decision depends on.
+    fn check_consent<'a>(
+        &'a mut self, req: &'a mut OAuthRequest, solicitation: Solicitation<'a>,
+    ) -> Pin<Box<dyn Future<Output = OwnerConsent<OAuthResponse>> + 'a>>;
+}
+
+impl<S> AsyncOwnerSolicitorPrimitive<OAuthRequest> for S
+where
+    S: AsyncOwnerSolicitor,
+{
+    fn check_consent<'a>(
+        &'a mut self, req: &'a mut OAuthRequest, solicitation: Solicitation<'a>,
+    ) -> Pin<Box<dyn Future<Output = OwnerConsent<OAuthResponse>> + 'a>> {
+        AsyncOwnerSolicitor::check_consent(self, req, solicitation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxide_auth::endpoint::PreGrant;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+    use url::Url;
+
+    /// Polls a future that's expected to resolve on its first poll, without pulling in an async
+    /// executor crate just for this test.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        fn noop(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        let waker = unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) };
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("test solicitor did not resolve synchronously"),
+        }
+    }
+
+    fn dummy_request() -> OAuthRequest {
+        OAuthRequest { auth: None, query: None, body: None }
+    }
+
+    // NB: assumes `Solicitation::new(&PreGrant, Option<&str>)` is a public constructor; the
+    // flow-facing types in `oxide_auth::endpoint` are otherwise only ever handed to consumers,
+    // never built by them, so adjust this if that constructor isn't actually public.
+    fn dummy_solicitation(pre_grant: &PreGrant) -> Solicitation<'_> {
+        Solicitation::new(pre_grant, None)
+    }
+
+    struct FixedConsent(OwnerConsent<OAuthResponse>);
+
+    impl AsyncOwnerSolicitor for FixedConsent {
+        fn check_consent<'a>(
+            &'a mut self, _req: &'a mut OAuthRequest, _solicitation: Solicitation<'a>,
+        ) -> Pin<Box<dyn Future<Output = OwnerConsent<OAuthResponse>> + 'a>> {
+            let consent = match &self.0 {
+                OwnerConsent::Authorized(owner) => OwnerConsent::Authorized(owner.clone()),
+                OwnerConsent::Denied => OwnerConsent::Denied,
+                OwnerConsent::InProgress(response) => OwnerConsent::InProgress(response.clone()),
+            };
+            Box::pin(async move { consent })
+        }
+    }
+
+    #[test]
+    fn blanket_impl_forwards_the_inner_solicitors_decision() {
+        let pre_grant = PreGrant {
+            client_id: "client".into(),
+            redirect_uri: Url::parse("https://example.com/callback").unwrap(),
+            scope: "read".parse().unwrap(),
+        };
+
+        let mut solicitor = FixedConsent(OwnerConsent::Authorized("resource-owner".into()));
+        let mut req = dummy_request();
+        let solicitation = dummy_solicitation(&pre_grant);
+
+        let consent = block_on(AsyncOwnerSolicitorPrimitive::check_consent(
+            &mut solicitor,
+            &mut req,
+            solicitation,
+        ));
+
+        match consent {
+            OwnerConsent::Authorized(owner) => assert_eq!(owner, "resource-owner"),
+            _ => panic!("expected the inner solicitor's Authorized decision to pass through"),
+        }
+    }
+
+    #[test]
+    fn blanket_impl_forwards_a_denial() {
+        let pre_grant = PreGrant {
+            client_id: "client".into(),
+            redirect_uri: Url::parse("https://example.com/callback").unwrap(),
+            scope: "read".parse().unwrap(),
+        };
+
+        let mut solicitor = FixedConsent(OwnerConsent::Denied);
+        let mut req = dummy_request();
+        let solicitation = dummy_solicitation(&pre_grant);
+
+        let consent = block_on(AsyncOwnerSolicitorPrimitive::check_consent(
+            &mut solicitor,
+            &mut req,
+            solicitation,
+        ));
+
+        assert!(matches!(consent, OwnerConsent::Denied));
+    }
+}